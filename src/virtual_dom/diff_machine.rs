@@ -0,0 +1,185 @@
+//! A cooperative, resumable driver for `VDiff`.
+//!
+//! `VList::apply` used to reconcile its children by recursing straight
+//! through `VDiff::apply`/`detach`, which always ran to completion
+//! synchronously — a list with thousands of children froze the page for as
+//! long as it took to walk all of them. `DiffMachine` replaces that
+//! recursion with an explicit work stack of `Instruction`s: each child is
+//! scheduled rather than diffed immediately, and the machine can process
+//! the stack in bounded batches, checking a `Budget` between them and
+//! yielding back to the caller when it's spent. Both of `VList`'s
+//! reconciliation paths (positional and keyed) schedule their children on
+//! a `DiffMachine` rather than recursing directly, so neither skips this
+//! machinery.
+//!
+//! `resume` processes one budget's worth of instructions and reports
+//! whether more remain, so an idle-callback scheduler can call it again on
+//! the next frame instead of blocking. `run_to_completion` is implemented
+//! on top of it (with an unbounded budget) rather than stepping the stack
+//! directly, so the two entry points share one instruction-stepping path
+//! instead of `resume` being a second, untested way to drive the same
+//! loop.
+//!
+//! That said, nothing in this tree *resumes across a yield* yet: both of
+//! `VList`'s paths call `run_to_completion`, and there's no idle-callback
+//! scheduler in this source tree to call `resume` with a bounded budget
+//! instead. `VComp`/`VList`'s instructions also borrow the tree they're
+//! diffing (`Instruction::Diff` holds `&mut VNode<COMP>`), so a caller that
+//! wants to actually yield to the browser between batches — rather than
+//! just being able to, as `resume` already allows — would need the
+//! scheduler to own the tree across that yield, which is a bigger change
+//! than this module. `resume`/`Budget` are the primitive that change would
+//! drive; large lists still reconcile in one synchronous pass today.
+
+use super::dom_edit::{DomEdit, EditApplier};
+use super::VNode;
+use crate::html::{Component, Scope};
+use stdweb::web::{Element, Node};
+
+/// Bounds how much work a single `DiffMachine::resume` call may do before
+/// yielding back to the scheduler.
+#[derive(Debug, Clone, Copy)]
+pub struct Budget {
+    /// Maximum number of instructions to process before yielding.
+    pub instructions: usize,
+}
+
+impl Default for Budget {
+    fn default() -> Self {
+        Budget { instructions: 64 }
+    }
+}
+
+/// A single unit of scheduled diffing work.
+enum Instruction<'a, COMP: Component> {
+    /// Diff `left` against `ancestor`, threading the machine's current
+    /// `last_sibling` through as the `previous_sibling`.
+    Diff {
+        left: &'a mut VNode<COMP>,
+        ancestor: Option<VNode<COMP>>,
+    },
+    /// Detach `node` (and everything beneath it).
+    Detach { node: VNode<COMP> },
+}
+
+/// A resumable stack machine driving `VDiff` for one parent element.
+///
+/// Instructions are processed LIFO, so callers that care about left-to-right
+/// ordering (as `VList` does, to thread `previous_sibling` correctly) must
+/// push them in reverse.
+pub struct DiffMachine<'a, COMP: Component> {
+    parent: Element,
+    scope: Scope<COMP>,
+    applier: &'a mut dyn EditApplier,
+    stack: Vec<Instruction<'a, COMP>>,
+    last_sibling: Option<Node>,
+    edits: Vec<DomEdit>,
+    /// The node produced by each processed `Diff` instruction, in the
+    /// order the instructions were processed, i.e. the reverse of push
+    /// order (see `run_to_completion_with_results`).
+    results: Vec<Option<Node>>,
+}
+
+impl<'a, COMP: Component> DiffMachine<'a, COMP> {
+    /// Creates a machine for diffing children of `parent`, starting from
+    /// `previous_sibling`. `applier` performs every mutation the machine
+    /// schedules.
+    pub fn new(
+        parent: Element,
+        scope: Scope<COMP>,
+        applier: &'a mut dyn EditApplier,
+        previous_sibling: Option<Node>,
+    ) -> Self {
+        DiffMachine {
+            parent,
+            scope,
+            applier,
+            stack: Vec::new(),
+            last_sibling: previous_sibling,
+            edits: Vec::new(),
+            results: Vec::new(),
+        }
+    }
+
+    /// Schedules `left` to be diffed against `ancestor`.
+    pub fn push_diff(&mut self, left: &'a mut VNode<COMP>, ancestor: Option<VNode<COMP>>) {
+        self.stack.push(Instruction::Diff { left, ancestor });
+    }
+
+    /// Schedules `node` to be detached.
+    pub fn push_detach(&mut self, node: VNode<COMP>) {
+        self.stack.push(Instruction::Detach { node });
+    }
+
+    /// Runs every scheduled instruction to completion, ignoring the budget.
+    /// Used for the initial mount and for tests, where there's no scheduler
+    /// to resume from later. Drives the same `resume` a scheduler would
+    /// call, just with a budget large enough to never yield.
+    pub fn run_to_completion(mut self) -> (Option<Node>, Vec<DomEdit>) {
+        let unbounded = Budget {
+            instructions: usize::MAX,
+        };
+        while self.resume(unbounded) {}
+        (self.last_sibling, self.edits)
+    }
+
+    /// Like `run_to_completion`, but also returns the node produced by
+    /// each scheduled `Diff` instruction, in processing order (the reverse
+    /// of push order, since the stack is LIFO). `VList::apply_keyed` needs
+    /// these alongside the edits to run its move pass.
+    pub fn run_to_completion_with_results(mut self) -> (Option<Node>, Vec<Option<Node>>, Vec<DomEdit>) {
+        let unbounded = Budget {
+            instructions: usize::MAX,
+        };
+        while self.resume(unbounded) {}
+        (self.last_sibling, self.results, self.edits)
+    }
+
+    /// Processes up to `budget.instructions` instructions, then reports
+    /// whether work remains (i.e. whether the caller should `resume` again
+    /// on the next idle callback).
+    pub fn resume(&mut self, budget: Budget) -> bool {
+        let mut remaining = budget.instructions;
+        while remaining > 0 && self.step() {
+            remaining -= 1;
+        }
+        !self.stack.is_empty()
+    }
+
+    /// The node produced by the most recently processed `Diff` instruction
+    /// (`Detach` instructions leave it unchanged), i.e.
+    /// the `previous_sibling` the next diff (inside or outside this
+    /// machine) should thread through.
+    pub fn last_sibling(&self) -> Option<&Node> {
+        self.last_sibling.as_ref()
+    }
+
+    /// Takes the edits accumulated so far, leaving the buffer empty.
+    pub fn take_edits(&mut self) -> Vec<DomEdit> {
+        std::mem::take(&mut self.edits)
+    }
+
+    /// Processes one instruction. Returns `false` when the stack was empty.
+    fn step(&mut self) -> bool {
+        match self.stack.pop() {
+            Some(Instruction::Diff { left, ancestor }) => {
+                let node = left.apply(
+                    &self.parent,
+                    self.last_sibling.as_ref(),
+                    ancestor,
+                    &self.scope,
+                    &mut *self.applier,
+                    &mut self.edits,
+                );
+                self.last_sibling = node.clone();
+                self.results.push(node);
+                true
+            }
+            Some(Instruction::Detach { mut node }) => {
+                node.detach(&self.parent, &mut *self.applier, &mut self.edits);
+                true
+            }
+            None => false,
+        }
+    }
+}
@@ -0,0 +1,91 @@
+//! This module contains the `DomEdit` enum and the appliers that execute it.
+//!
+//! `VDiff` implementations no longer call `stdweb` directly to mutate the
+//! DOM; instead they describe each mutation as a `DomEdit` and hand it to
+//! whichever [`EditApplier`](trait.EditApplier.html) the caller that kicked
+//! off the diff chose, via [`record`](fn.record.html). [`WebApplier`]
+//! (struct.WebApplier.html) performs the mutation against a live browser
+//! DOM, exactly like the inline `stdweb` calls this replaces, and is what
+//! the browser mount path passes in. Swapping in a different
+//! `EditApplier` is how a caller would plug in e.g. an in-memory applier
+//! for snapshot testing; note that today `DomEdit`'s `Node`/`TextNode`
+//! fields are still concrete `stdweb` types, so a non-browser backend
+//! (an HTML-string applier for SSR, say) would additionally need those
+//! generalized — that's not done here.
+use crate::html::NodeRef;
+use stdweb::web::{document, Element, INode, Node};
+
+/// A single low-level DOM mutation produced while diffing a `VNode` tree.
+#[derive(Debug, Clone)]
+pub enum DomEdit {
+    /// Create a text node holding `text`.
+    CreateTextNode { text: String },
+    /// Create an empty text node, reserving a DOM slot for content that
+    /// isn't ready to render yet (a mounting `VComp`). An empty `VList`
+    /// reserves its slot with an empty `VText` instead, which emits
+    /// `CreateTextNode`, not this edit.
+    CreatePlaceholder,
+    /// Insert `node` before `before`.
+    InsertBefore { node: Node, before: Node },
+    /// Append `node` as the parent's last child.
+    AppendChild { node: Node },
+    /// Remove `node` from the parent.
+    RemoveNode { node: Node },
+    /// Associate `node` with `node_ref` without creating or moving anything.
+    /// None of the `VDiff` implementations in this module emit it.
+    SetNode { node_ref: NodeRef, node: Node },
+}
+
+/// Executes a `DomEdit`, returning the `Node` it produced or touched, if
+/// any.
+pub trait EditApplier {
+    /// Applies `edit` under `parent`.
+    fn apply(&mut self, parent: &Element, edit: DomEdit) -> Option<Node>;
+}
+
+/// Applies edits against a live browser DOM via `stdweb`.
+#[derive(Debug, Default)]
+pub struct WebApplier;
+
+impl EditApplier for WebApplier {
+    fn apply(&mut self, parent: &Element, edit: DomEdit) -> Option<Node> {
+        match edit {
+            DomEdit::CreateTextNode { text } => Some(document().create_text_node(&text).into()),
+            DomEdit::CreatePlaceholder => Some(document().create_text_node("").into()),
+            DomEdit::InsertBefore { node, before } => {
+                parent
+                    .insert_before(&node, &before)
+                    .expect("can't insert node");
+                Some(node)
+            }
+            DomEdit::AppendChild { node } => {
+                parent.append_child(&node);
+                Some(node)
+            }
+            DomEdit::RemoveNode { node } => {
+                parent.remove_child(&node).expect("can't remove node");
+                None
+            }
+            DomEdit::SetNode { node_ref, node } => {
+                node_ref.set(Some(node.clone()));
+                Some(node)
+            }
+        }
+    }
+}
+
+/// Runs `edit` through `applier` and appends it to `edits`, so the same
+/// mutation sequence is also available for inspection or replay afterwards.
+/// Like the rest of `VDiff`, this never picks an applier itself — the
+/// caller that started the diff (the browser mount path, a test harness,
+/// ...) decides which one to thread through.
+pub fn record(
+    edits: &mut Vec<DomEdit>,
+    applier: &mut dyn EditApplier,
+    parent: &Element,
+    edit: DomEdit,
+) -> Option<Node> {
+    let node = applier.apply(parent, edit.clone());
+    edits.push(edit);
+    node
+}
@@ -0,0 +1,58 @@
+//! This module contains the implementation of keys for virtual nodes.
+
+use std::borrow::Cow;
+use std::fmt;
+use std::ops::Deref;
+
+/// Represents the (optional) key of Yew's virtual nodes.
+///
+/// Elements carrying a `Key` keep their identity across re-renders: a
+/// `VList` whose children all carry keys reconciles them by key instead of
+/// by position, so reordering a list reuses and moves existing nodes
+/// instead of tearing them down and rebuilding them from scratch.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Key(Cow<'static, str>);
+
+impl Deref for Key {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl From<String> for Key {
+    fn from(key: String) -> Self {
+        Key(Cow::Owned(key))
+    }
+}
+
+impl From<&'static str> for Key {
+    fn from(key: &'static str) -> Self {
+        Key(Cow::Borrowed(key))
+    }
+}
+
+impl From<usize> for Key {
+    fn from(key: usize) -> Self {
+        Key(Cow::Owned(key.to_string()))
+    }
+}
+
+impl From<u32> for Key {
+    fn from(key: u32) -> Self {
+        Key(Cow::Owned(key.to_string()))
+    }
+}
+
+impl From<u64> for Key {
+    fn from(key: u64) -> Self {
+        Key(Cow::Owned(key.to_string()))
+    }
+}
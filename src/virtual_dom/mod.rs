@@ -0,0 +1,53 @@
+//! This module contains Yew's virtual DOM implementation.
+
+mod diff_machine;
+mod dom_edit;
+mod key;
+mod vcomp;
+mod vlist;
+mod vnode;
+mod vtext;
+
+pub use diff_machine::{Budget, DiffMachine};
+pub use dom_edit::{record, DomEdit, EditApplier, WebApplier};
+pub use key::Key;
+pub use vcomp::{ScopeHolder, Transformer, VChild, VComp};
+pub use vlist::VList;
+pub use vnode::VNode;
+pub use vtext::VText;
+
+use crate::html::{Component, Scope};
+use stdweb::web::{Element, Node};
+
+/// This trait provides a common interface to diff and apply changes to the
+/// DOM for any virtual node. Implementors describe each mutation as a
+/// `DomEdit` pushed into the caller-supplied `edits` buffer, rather than
+/// calling `stdweb` directly.
+pub trait VDiff {
+    /// The concrete component type the associated virtual node is bound to.
+    type Component: Component;
+
+    /// Remove itself from the parent, returning the next sibling so the
+    /// caller can keep threading `previous_sibling` through. `applier`
+    /// performs the removal; `edits` records it.
+    fn detach(
+        &mut self,
+        parent: &Element,
+        applier: &mut dyn EditApplier,
+        edits: &mut Vec<DomEdit>,
+    ) -> Option<Node>;
+
+    /// Scaffolds a DOM tree inside the `parent`, returning the node it
+    /// produced or reused so the caller can thread it on as the next
+    /// `previous_sibling`. `applier` performs every mutation; `edits`
+    /// records them.
+    fn apply(
+        &mut self,
+        parent: &Element,
+        previous_sibling: Option<&Node>,
+        ancestor: Option<VNode<Self::Component>>,
+        parent_scope: &Scope<Self::Component>,
+        applier: &mut dyn EditApplier,
+        edits: &mut Vec<DomEdit>,
+    ) -> Option<Node>;
+}
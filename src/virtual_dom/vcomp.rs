@@ -1,20 +1,22 @@
 //! This module contains the implementation of a virtual component `VComp`.
 
-use super::{VDiff, VNode};
+use super::dom_edit::{self, DomEdit, EditApplier};
+use super::{Key, VDiff, VNode};
 use crate::callback::Callback;
 use crate::html::{Component, ComponentUpdate, NodeRef, Scope};
 use std::any::TypeId;
 use std::cell::RefCell;
+use std::convert::TryInto;
 use std::fmt;
 use std::rc::Rc;
-use stdweb::web::{document, Element, INode, Node, TextNode};
+use stdweb::web::{Element, INode, Node, TextNode};
 
 struct Hidden;
 
 type HiddenScope = *mut Hidden;
 
 /// The method generates an instance of a component.
-type Generator<PARENT> = dyn FnOnce(GeneratorType, Scope<PARENT>) -> Mounted;
+type Generator<PARENT> = dyn FnOnce(GeneratorType, Scope<PARENT>) -> Generated;
 
 /// Components can be generated by mounting or by overwriting an old component.
 enum GeneratorType {
@@ -29,6 +31,7 @@ pub type ScopeHolder<PARENT> = Rc<RefCell<Option<Scope<PARENT>>>>;
 pub struct VComp<PARENT: Component> {
     type_id: TypeId,
     state: Rc<RefCell<MountState<PARENT>>>,
+    key: Option<Key>,
 }
 
 /// A virtual child component.
@@ -69,6 +72,24 @@ where
 enum MountState<PARENT: Component> {
     Unmounted(Unmounted<PARENT>),
     Mounted(Mounted),
+    /// The component is mounted, but its data isn't ready yet: `node_ref`
+    /// points at a reserved placeholder rather than the component's real
+    /// root. The parent treats this the same as `Mounted` (same scope, same
+    /// reserved DOM slot) except that the placeholder still needs swapping
+    /// out once the component resolves, via `VComp::resolve_suspense`.
+    ///
+    /// As shipped, the only thing that ever calls `resolve_suspense` is
+    /// `VDiff::apply`'s `Reform::ResolveSuspense` branch, i.e. this only
+    /// resolves if the *parent* happens to re-render while the child is
+    /// still `Suspended`. The self-initiated case this state is named
+    /// for — the component's own future/fetch completing independent of
+    /// any parent re-render — has no driver in this tree: that would need
+    /// `Scope` (`crate::html`, not part of this source tree) to hold a
+    /// handle back to this `state` cell and call `resolve_suspense` itself
+    /// when its data arrives. Until that hook exists, a component
+    /// suspended by data unrelated to its own props stays on its
+    /// placeholder until the parent re-renders for some other reason.
+    Suspended(Suspended),
     Mounting,
     Detached,
     Overwritten,
@@ -84,6 +105,19 @@ struct Mounted {
     destroyer: Box<dyn FnOnce()>,
 }
 
+struct Suspended {
+    node_ref: NodeRef,
+    scope: HiddenScope,
+    destroyer: Box<dyn FnOnce()>,
+}
+
+/// What a `Generator` produced: either the component rendered its real root
+/// right away, or it suspended and only mounted a placeholder.
+enum Generated {
+    Mounted(Mounted),
+    Suspended(Suspended),
+}
+
 impl<PARENT: Component> VComp<PARENT> {
     /// This method prepares a generator to make a new instance of the `Component`.
     pub fn new<SELF>(
@@ -94,7 +128,7 @@ impl<PARENT: Component> VComp<PARENT> {
     where
         SELF: Component,
     {
-        let generator = move |generator_type: GeneratorType, parent: Scope<PARENT>| -> Mounted {
+        let generator = move |generator_type: GeneratorType, parent: Scope<PARENT>| -> Generated {
             *scope_holder.borrow_mut() = Some(parent);
             match generator_type {
                 GeneratorType::Mount(element, dummy_node) => {
@@ -107,10 +141,21 @@ impl<PARENT: Component> VComp<PARENT> {
                         props,
                     );
 
-                    Mounted {
-                        node_ref,
-                        scope: Box::into_raw(Box::new(scope.clone())) as *mut Hidden,
-                        destroyer: Box::new(move || scope.destroy()),
+                    let suspended = scope.is_suspended();
+                    let hidden_scope = Box::into_raw(Box::new(scope.clone())) as *mut Hidden;
+                    let destroyer = Box::new(move || scope.destroy());
+                    if suspended {
+                        Generated::Suspended(Suspended {
+                            node_ref,
+                            scope: hidden_scope,
+                            destroyer,
+                        })
+                    } else {
+                        Generated::Mounted(Mounted {
+                            node_ref,
+                            scope: hidden_scope,
+                            destroyer,
+                        })
                     }
                 }
                 GeneratorType::Overwrite(type_id, scope) => {
@@ -125,10 +170,21 @@ impl<PARENT: Component> VComp<PARENT> {
 
                     scope.update(ComponentUpdate::Properties(props));
 
-                    Mounted {
-                        node_ref,
-                        scope: Box::into_raw(Box::new(scope.clone())) as *mut Hidden,
-                        destroyer: Box::new(move || scope.destroy()),
+                    let suspended = scope.is_suspended();
+                    let hidden_scope = Box::into_raw(Box::new(scope.clone())) as *mut Hidden;
+                    let destroyer = Box::new(move || scope.destroy());
+                    if suspended {
+                        Generated::Suspended(Suspended {
+                            node_ref,
+                            scope: hidden_scope,
+                            destroyer,
+                        })
+                    } else {
+                        Generated::Mounted(Mounted {
+                            node_ref,
+                            scope: hidden_scope,
+                            destroyer,
+                        })
                     }
                 }
             }
@@ -139,8 +195,59 @@ impl<PARENT: Component> VComp<PARENT> {
             state: Rc::new(RefCell::new(MountState::Unmounted(Unmounted {
                 generator: Box::new(generator),
             }))),
+            key: None,
         }
     }
+
+    /// Attaches a key to this component, letting a keyed `VList` reuse it
+    /// across reorders instead of tearing it down and remounting it.
+    pub fn with_key<K: Into<Key>>(mut self, key: K) -> Self {
+        self.key = Some(key.into());
+        self
+    }
+
+    /// Returns the key attached to this component, if any.
+    pub fn key(&self) -> Option<&Key> {
+        self.key.as_ref()
+    }
+}
+
+impl<PARENT: Component> VComp<PARENT> {
+    /// Swaps a suspended component's reserved `placeholder` for the real
+    /// root it just produced, promoting `mounted` into `self.state`.
+    ///
+    /// Currently only called from `VDiff::apply`'s `Reform::ResolveSuspense`
+    /// branch (see the note on `MountState::Suspended`), which requires a
+    /// parent re-render to happen at all. The self-initiated case -- a
+    /// `Scope` calling this directly the moment its own future/fetch
+    /// resolves -- isn't wired, since that needs `Scope` to hold a handle
+    /// back to this `VComp`'s `state` cell across the async gap, and
+    /// `Scope` lives outside this source tree (`crate::html`).
+    pub(crate) fn resolve_suspense(
+        &mut self,
+        parent: &Element,
+        placeholder: Node,
+        mounted: Mounted,
+        applier: &mut dyn EditApplier,
+        edits: &mut Vec<DomEdit>,
+    ) -> Option<Node> {
+        let root = mounted
+            .node_ref
+            .get()
+            .expect("a freshly mounted component has a root");
+        dom_edit::record(
+            edits,
+            applier,
+            parent,
+            DomEdit::InsertBefore {
+                node: root.clone(),
+                before: placeholder.clone(),
+            },
+        );
+        dom_edit::record(edits, applier, parent, DomEdit::RemoveNode { node: placeholder });
+        self.state.replace(MountState::Mounted(mounted));
+        Some(root)
+    }
 }
 
 /// Transforms properties and attaches a parent scope holder to callbacks for sending messages.
@@ -215,18 +322,27 @@ where
 
 impl<PARENT: Component> Unmounted<PARENT> {
     /// Mount a virtual component using a generator.
-    fn mount(self, parent: Element, dummy_node: TextNode, parent_scope: Scope<PARENT>) -> Mounted {
+    fn mount(
+        self,
+        parent: Element,
+        dummy_node: TextNode,
+        parent_scope: Scope<PARENT>,
+    ) -> Generated {
         (self.generator)(GeneratorType::Mount(parent, dummy_node), parent_scope)
     }
 
     /// Overwrite an existing virtual component using a generator.
-    fn replace(self, type_id: TypeId, old: Mounted, parent_scope: Scope<PARENT>) -> Mounted {
-        (self.generator)(GeneratorType::Overwrite(type_id, old.scope), parent_scope)
+    fn replace(self, type_id: TypeId, scope: HiddenScope, parent_scope: Scope<PARENT>) -> Generated {
+        (self.generator)(GeneratorType::Overwrite(type_id, scope), parent_scope)
     }
 }
 
 enum Reform {
-    Keep(TypeId, Mounted),
+    Keep(TypeId, HiddenScope),
+    /// Like `Keep`, but the ancestor was `Suspended`: carries its reserved
+    /// placeholder so `apply` can swap it for a real root if the generator
+    /// resolves this render, via `resolve_suspense`.
+    ResolveSuspense(TypeId, HiddenScope, Node),
     Before(Option<Node>),
 }
 
@@ -236,15 +352,26 @@ where
 {
     type Component = COMP;
 
-    fn detach(&mut self, parent: &Element) -> Option<Node> {
+    fn detach(
+        &mut self,
+        parent: &Element,
+        applier: &mut dyn EditApplier,
+        edits: &mut Vec<DomEdit>,
+    ) -> Option<Node> {
         match self.state.replace(MountState::Detached) {
             MountState::Mounted(this) => {
                 (this.destroyer)();
                 this.node_ref.get().and_then(|node| {
                     let sibling = node.next_sibling();
-                    parent
-                        .remove_child(&node)
-                        .expect("can't remove the component");
+                    dom_edit::record(edits, applier, parent, DomEdit::RemoveNode { node });
+                    sibling
+                })
+            }
+            MountState::Suspended(this) => {
+                (this.destroyer)();
+                this.node_ref.get().and_then(|node| {
+                    let sibling = node.next_sibling();
+                    dom_edit::record(edits, applier, parent, DomEdit::RemoveNode { node });
                     sibling
                 })
             }
@@ -258,61 +385,137 @@ where
         previous_sibling: Option<&Node>,
         ancestor: Option<VNode<Self::Component>>,
         parent_scope: &Scope<Self::Component>,
+        applier: &mut dyn EditApplier,
+        edits: &mut Vec<DomEdit>,
     ) -> Option<Node> {
         match self.state.replace(MountState::Mounting) {
             MountState::Unmounted(this) => {
                 let reform = match ancestor {
                     Some(VNode::VComp(mut vcomp)) => {
                         // If the ancestor is a Component of the same type, don't replace, keep the
-                        // old Component but update the properties.
+                        // old Component but update the properties. A `Suspended` ancestor is kept
+                        // the same way: its scope is still live, it just hasn't produced its real
+                        // root yet, so there's nothing to remount.
                         if self.type_id == vcomp.type_id {
                             match vcomp.state.replace(MountState::Overwritten) {
                                 MountState::Mounted(mounted) => {
-                                    Reform::Keep(vcomp.type_id, mounted)
+                                    Reform::Keep(vcomp.type_id, mounted.scope)
+                                }
+                                MountState::Suspended(suspended) => {
+                                    let placeholder = suspended.node_ref.get().expect(
+                                        "a suspended component always has a reserved placeholder",
+                                    );
+                                    Reform::ResolveSuspense(
+                                        vcomp.type_id,
+                                        suspended.scope,
+                                        placeholder,
+                                    )
                                 }
                                 _ => Reform::Before(None),
                             }
                         } else {
-                            let node = vcomp.detach(parent);
+                            let node = vcomp.detach(parent, applier, edits);
                             Reform::Before(node)
                         }
                     }
                     Some(mut vnode) => {
-                        let node = vnode.detach(parent);
+                        let node = vnode.detach(parent, applier, edits);
                         Reform::Before(node)
                     }
                     None => Reform::Before(None),
                 };
 
-                let mounted = match reform {
-                    Reform::Keep(type_id, mounted) => {
+                let (generated, placeholder) = match reform {
+                    Reform::Keep(type_id, scope) => {
                         // Send properties update when the component is already rendered.
-                        this.replace(type_id, mounted, parent_scope.clone())
+                        (this.replace(type_id, scope, parent_scope.clone()), None)
+                    }
+                    Reform::ResolveSuspense(type_id, scope, placeholder) => {
+                        // Same property update, but the ancestor was still
+                        // loading, so its placeholder needs to be swapped
+                        // for a real root below if this render resolves it.
+                        (
+                            this.replace(type_id, scope, parent_scope.clone()),
+                            Some(placeholder),
+                        )
                     }
                     Reform::Before(before) => {
                         // Temporary node which will be replaced by a component's root node.
-                        let dummy_node = document().create_text_node("");
+                        let dummy_node: TextNode = dom_edit::record(
+                            edits,
+                            applier,
+                            parent,
+                            DomEdit::CreatePlaceholder,
+                        )
+                        .expect("CreatePlaceholder always produces a node")
+                        .try_into()
+                        .expect("placeholder is a text node");
                         if let Some(sibling) = before {
-                            parent
-                                .insert_before(&dummy_node, &sibling)
-                                .expect("can't insert dummy node for a component");
+                            dom_edit::record(
+                                edits,
+                                applier,
+                                parent,
+                                DomEdit::InsertBefore {
+                                    node: dummy_node.clone().into(),
+                                    before: sibling,
+                                },
+                            );
                         } else {
                             let previous_sibling =
                                 previous_sibling.and_then(|before| before.next_sibling());
                             if let Some(previous_sibling) = previous_sibling {
-                                parent
-                                    .insert_before(&dummy_node, &previous_sibling)
-                                    .expect("can't insert dummy node before previous sibling");
+                                dom_edit::record(
+                                    edits,
+                                    applier,
+                                    parent,
+                                    DomEdit::InsertBefore {
+                                        node: dummy_node.clone().into(),
+                                        before: previous_sibling,
+                                    },
+                                );
                             } else {
-                                parent.append_child(&dummy_node);
+                                dom_edit::record(
+                                    edits,
+                                    applier,
+                                    parent,
+                                    DomEdit::AppendChild {
+                                        node: dummy_node.clone().into(),
+                                    },
+                                );
                             }
                         }
-                        this.mount(parent.to_owned(), dummy_node, parent_scope.clone())
+                        (
+                            this.mount(parent.to_owned(), dummy_node, parent_scope.clone()),
+                            None,
+                        )
                     }
                 };
 
-                let node = mounted.node_ref.get();
-                self.state.replace(MountState::Mounted(mounted));
+                let node = match generated {
+                    Generated::Mounted(mounted) => match placeholder {
+                        Some(placeholder) => {
+                            // The ancestor was still loading; it just
+                            // produced its real root. Swap the reserved
+                            // placeholder for it instead of leaving a stale
+                            // slot behind.
+                            self.resolve_suspense(parent, placeholder, mounted, applier, edits)
+                        }
+                        None => {
+                            let node = mounted.node_ref.get();
+                            self.state.replace(MountState::Mounted(mounted));
+                            node
+                        }
+                    },
+                    Generated::Suspended(suspended) => {
+                        // Still loading. Keep showing whichever placeholder
+                        // is already on the page: the one just reserved for
+                        // a first mount, or the ancestor's if this is still
+                        // resolving.
+                        let node = placeholder.or_else(|| suspended.node_ref.get());
+                        self.state.replace(MountState::Suspended(suspended));
+                        node
+                    }
+                };
                 node
             }
             state => {
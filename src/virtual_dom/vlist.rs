@@ -1,6 +1,9 @@
 //! This module contains fragments implementation.
-use super::{VDiff, VNode, VText};
+use super::diff_machine::DiffMachine;
+use super::dom_edit::{self, DomEdit, EditApplier};
+use super::{Key, VDiff, VNode, VText};
 use crate::html::{Component, Scope};
+use std::collections::{HashMap, HashSet};
 use std::ops::{Deref, DerefMut};
 use stdweb::web::{Element, Node};
 
@@ -11,6 +14,9 @@ pub struct VList<COMP: Component> {
     pub no_siblings: bool,
     /// The list of children nodes. Which also could have their own children.
     pub children: Vec<VNode<COMP>>,
+    /// An optional key, letting this fragment be reused by a keyed parent
+    /// `VList` across reorders.
+    pub key: Option<Key>,
 }
 
 impl<COMP: Component> Deref for VList<COMP> {
@@ -45,6 +51,7 @@ impl<COMP: Component> VList<COMP> {
         VList {
             no_siblings,
             children: Vec::new(),
+            key: None,
         }
     }
 
@@ -52,15 +59,26 @@ impl<COMP: Component> VList<COMP> {
     pub fn add_child(&mut self, child: VNode<COMP>) {
         self.children.push(child);
     }
+
+    /// Attaches a key to this fragment.
+    pub fn with_key<K: Into<Key>>(mut self, key: K) -> Self {
+        self.key = Some(key.into());
+        self
+    }
 }
 
 impl<COMP: Component> VDiff for VList<COMP> {
     type Component = COMP;
 
-    fn detach(&mut self, parent: &Element) -> Option<Node> {
+    fn detach(
+        &mut self,
+        parent: &Element,
+        applier: &mut dyn EditApplier,
+        edits: &mut Vec<DomEdit>,
+    ) -> Option<Node> {
         let mut last_sibling = None;
         for mut child in self.children.drain(..) {
-            last_sibling = child.detach(parent);
+            last_sibling = child.detach(parent, applier, edits);
         }
         last_sibling
     }
@@ -71,10 +89,12 @@ impl<COMP: Component> VDiff for VList<COMP> {
         previous_sibling: Option<&Node>,
         ancestor: Option<VNode<Self::Component>>,
         parent_scope: &Scope<Self::Component>,
+        applier: &mut dyn EditApplier,
+        edits: &mut Vec<DomEdit>,
     ) -> Option<Node> {
         // Reuse previous_sibling, because fragment reuse parent
-        let mut previous_sibling = previous_sibling.cloned();
-        let mut rights = {
+        let previous_sibling = previous_sibling.cloned();
+        let rights = {
             match ancestor {
                 // If element matched this type
                 Some(VNode::VList(vlist)) => {
@@ -99,29 +119,200 @@ impl<COMP: Component> VDiff for VList<COMP> {
             self.children.push(placeholder.into());
         }
 
-        // Process children
-        let mut lefts = self.children.iter_mut();
-        let mut rights = rights.drain(..);
-        loop {
-            match (lefts.next(), rights.next()) {
-                (Some(left), Some(right)) => {
-                    previous_sibling = left.apply(
-                        parent,
-                        previous_sibling.as_ref(),
-                        Some(right),
-                        &parent_scope,
-                    );
+        let keyed = !rights.is_empty()
+            && self.children.iter().all(|node| node.key().is_some())
+            && rights.iter().all(|node| node.key().is_some());
+
+        if keyed {
+            self.apply_keyed(parent, previous_sibling, rights, &parent_scope, applier, edits)
+        } else {
+            // Pair up children positionally; anything left over on either
+            // side is a pure create (extra lefts) or a pure detach (extra
+            // rights). Schedule the work on a `DiffMachine` instead of
+            // recursing directly; the machine is run to completion here, so
+            // the whole list is still reconciled in one synchronous pass.
+            let mut lefts = self.children.iter_mut();
+            let mut rights = rights.into_iter();
+            let mut pairs = Vec::new();
+            let mut stale = Vec::new();
+            loop {
+                match (lefts.next(), rights.next()) {
+                    (Some(left), Some(right)) => pairs.push((left, Some(right))),
+                    (Some(left), None) => pairs.push((left, None)),
+                    (None, Some(right)) => stale.push(right),
+                    (None, None) => break,
+                }
+            }
+
+            let mut machine =
+                DiffMachine::new(parent.clone(), parent_scope.clone(), applier, previous_sibling);
+            for node in stale.into_iter().rev() {
+                machine.push_detach(node);
+            }
+            for (left, ancestor) in pairs.into_iter().rev() {
+                machine.push_diff(left, ancestor);
+            }
+            let (node, new_edits) = machine.run_to_completion();
+            edits.extend(new_edits);
+            node
+        }
+    }
+}
+
+impl<COMP: Component> VList<COMP> {
+    /// Reconciles `self.children` against `rights` (the ancestor's children)
+    /// by key instead of by position.
+    ///
+    /// Each new child is paired with its previous incarnation (if any) by
+    /// looking it up in a `HashMap` keyed by `Key`; old children with no
+    /// match in the new list are detached. The old indices of the surviving
+    /// (reused) children are then fed through a longest-increasing-subsequence
+    /// search: nodes in the LIS are already in the right relative order and
+    /// don't need to move, while every other reused node and every freshly
+    /// created node is repositioned with a single `insert_before`, walking
+    /// the new list right-to-left so the next already-placed sibling is
+    /// always known.
+    fn apply_keyed(
+        &mut self,
+        parent: &Element,
+        previous_sibling: Option<Node>,
+        rights: Vec<VNode<COMP>>,
+        parent_scope: &Scope<COMP>,
+        applier: &mut dyn EditApplier,
+        edits: &mut Vec<DomEdit>,
+    ) -> Option<Node> {
+        let mut old_by_key: HashMap<Key, (usize, VNode<COMP>)> = HashMap::with_capacity(rights.len());
+        for (old_index, node) in rights.into_iter().enumerate() {
+            let key = node.key().cloned().expect("keyed reconciliation requires a key");
+            if let Some((_, mut shadowed)) = old_by_key.insert(key, (old_index, node)) {
+                // Two old children shared a key; `insert` silently dropped
+                // `shadowed` from the map, so detach it here instead of
+                // leaking its DOM subtree. Keep the later duplicate as the
+                // canonical one, since it's what a real key collision in
+                // rendered output would usually mean (the earlier node is
+                // already stale).
+                shadowed.detach(parent, applier, edits);
+            }
+        }
+
+        // Pair each new child with its previous incarnation (if any), and
+        // remember the old index of every reused node.
+        let mut ancestors: Vec<Option<VNode<COMP>>> = Vec::with_capacity(self.children.len());
+        let mut old_indices: Vec<Option<usize>> = Vec::with_capacity(self.children.len());
+        for child in self.children.iter() {
+            let key = child.key().cloned().expect("keyed reconciliation requires a key");
+            match old_by_key.remove(&key) {
+                Some((old_index, old_node)) => {
+                    ancestors.push(Some(old_node));
+                    old_indices.push(Some(old_index));
                 }
-                (Some(left), None) => {
-                    previous_sibling =
-                        left.apply(parent, previous_sibling.as_ref(), None, &parent_scope);
+                None => {
+                    ancestors.push(None);
+                    old_indices.push(None);
                 }
-                (None, Some(ref mut right)) => {
-                    right.detach(parent);
+            }
+        }
+
+        // Anything left behind had no match in the new list.
+        for (_, (_, mut stale)) in old_by_key {
+            stale.detach(parent, applier, edits);
+        }
+
+        let survivors: Vec<usize> = old_indices.iter().filter_map(|index| *index).collect();
+        let lis = longest_increasing_subsequence(&survivors);
+        let keep_in_place: HashSet<usize> = {
+            let mut keep = HashSet::new();
+            let mut survivor_cursor = 0;
+            for (new_index, old_index) in old_indices.iter().enumerate() {
+                if old_index.is_some() {
+                    if lis.contains(&survivor_cursor) {
+                        keep.insert(new_index);
+                    }
+                    survivor_cursor += 1;
+                }
+            }
+            keep
+        };
+
+        // Patch content left-to-right first so every child has an up-to-date
+        // DOM node before the move pass repositions any of them. Scheduled
+        // on a `DiffMachine`, same as the positional path, so a keyed list
+        // doesn't skip the resumable machinery just because it took the
+        // by-key branch.
+        let mut machine =
+            DiffMachine::new(parent.clone(), parent_scope.clone(), applier, previous_sibling);
+        for (child, ancestor) in self.children.iter_mut().zip(ancestors.into_iter()).rev() {
+            machine.push_diff(child, ancestor);
+        }
+        let (_, nodes, new_edits) = machine.run_to_completion_with_results();
+        edits.extend(new_edits);
+
+        // Move pass: walk right-to-left so the reference node for
+        // `insert_before` (the next sibling already in its final position)
+        // is always known.
+        let mut next_sibling: Option<Node> = None;
+        for (index, node) in nodes.iter().enumerate().rev() {
+            if let Some(node) = node {
+                if !keep_in_place.contains(&index) {
+                    match next_sibling {
+                        Some(ref before) => {
+                            dom_edit::record(
+                                edits,
+                                applier,
+                                parent,
+                                DomEdit::InsertBefore {
+                                    node: node.clone(),
+                                    before: before.clone(),
+                                },
+                            );
+                        }
+                        None => {
+                            dom_edit::record(
+                                edits,
+                                applier,
+                                parent,
+                                DomEdit::AppendChild { node: node.clone() },
+                            );
+                        }
+                    }
                 }
-                (None, None) => break,
+                next_sibling = Some(node.clone());
             }
         }
-        previous_sibling
+
+        nodes.into_iter().rev().find_map(|node| node)
+    }
+}
+
+/// Returns the indices (into `sequence`) of one longest strictly increasing
+/// subsequence, found with the standard O(n log n) patience-sorting
+/// algorithm.
+fn longest_increasing_subsequence(sequence: &[usize]) -> Vec<usize> {
+    let mut tails: Vec<usize> = Vec::new();
+    let mut predecessors: Vec<Option<usize>> = vec![None; sequence.len()];
+
+    for (i, &value) in sequence.iter().enumerate() {
+        let pos = match tails.binary_search_by(|&t| sequence[t].cmp(&value)) {
+            Ok(pos) => pos,
+            Err(pos) => pos,
+        };
+
+        if pos > 0 {
+            predecessors[i] = Some(tails[pos - 1]);
+        }
+        if pos == tails.len() {
+            tails.push(i);
+        } else {
+            tails[pos] = i;
+        }
+    }
+
+    let mut lis = Vec::with_capacity(tails.len());
+    let mut cursor = tails.last().copied();
+    while let Some(i) = cursor {
+        lis.push(i);
+        cursor = predecessors[i];
     }
+    lis.reverse();
+    lis
 }
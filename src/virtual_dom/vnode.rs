@@ -0,0 +1,122 @@
+//! This module contains the implementation of `VNode`, the node type of
+//! Yew's virtual DOM tree.
+
+use super::dom_edit::{DomEdit, EditApplier};
+use super::{Key, VComp, VDiff, VList, VText};
+use crate::html::{Component, Scope};
+use std::fmt;
+use stdweb::web::{Element, INode, Node};
+
+/// Bundles every kind of node `VDiff` knows how to reconcile.
+pub enum VNode<COMP: Component> {
+    /// A virtual component.
+    VComp(VComp<COMP>),
+    /// A fragment of sibling nodes, reconciled together.
+    VList(VList<COMP>),
+    /// A virtual text node.
+    VText(VText),
+    /// A node that already exists in the DOM and is only held for
+    /// reference (e.g. the reserved placeholder used while a `VComp` is
+    /// still mounting).
+    VRef(Node),
+}
+
+impl<COMP: Component> VNode<COMP> {
+    /// Returns the key carried by this node, if any.
+    ///
+    /// `VList::apply` uses this to decide whether its children can be
+    /// reconciled by key instead of by position.
+    pub fn key(&self) -> Option<&Key> {
+        match self {
+            VNode::VComp(vcomp) => vcomp.key(),
+            VNode::VList(vlist) => vlist.key.as_ref(),
+            VNode::VText(vtext) => vtext.key(),
+            VNode::VRef(_) => None,
+        }
+    }
+}
+
+impl<COMP: Component> VDiff for VNode<COMP> {
+    type Component = COMP;
+
+    fn detach(
+        &mut self,
+        parent: &Element,
+        applier: &mut dyn EditApplier,
+        edits: &mut Vec<DomEdit>,
+    ) -> Option<Node> {
+        match self {
+            VNode::VComp(vcomp) => vcomp.detach(parent, applier, edits),
+            VNode::VList(vlist) => vlist.detach(parent, applier, edits),
+            VNode::VText(vtext) => vtext.detach(parent, applier, edits),
+            VNode::VRef(node) => node.next_sibling(),
+        }
+    }
+
+    fn apply(
+        &mut self,
+        parent: &Element,
+        previous_sibling: Option<&Node>,
+        ancestor: Option<VNode<COMP>>,
+        parent_scope: &Scope<COMP>,
+        applier: &mut dyn EditApplier,
+        edits: &mut Vec<DomEdit>,
+    ) -> Option<Node> {
+        match self {
+            VNode::VComp(vcomp) => {
+                vcomp.apply(parent, previous_sibling, ancestor, parent_scope, applier, edits)
+            }
+            VNode::VList(vlist) => {
+                vlist.apply(parent, previous_sibling, ancestor, parent_scope, applier, edits)
+            }
+            VNode::VText(vtext) => vtext.apply(parent, previous_sibling, ancestor, applier, edits),
+            VNode::VRef(node) => {
+                if let Some(mut old) = ancestor {
+                    old.detach(parent, applier, edits);
+                }
+                Some(node.clone())
+            }
+        }
+    }
+}
+
+impl<COMP: Component> From<VText> for VNode<COMP> {
+    fn from(vtext: VText) -> Self {
+        VNode::VText(vtext)
+    }
+}
+
+impl<COMP: Component> From<VComp<COMP>> for VNode<COMP> {
+    fn from(vcomp: VComp<COMP>) -> Self {
+        VNode::VComp(vcomp)
+    }
+}
+
+impl<COMP: Component> From<VList<COMP>> for VNode<COMP> {
+    fn from(vlist: VList<COMP>) -> Self {
+        VNode::VList(vlist)
+    }
+}
+
+impl<COMP: Component> fmt::Debug for VNode<COMP> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VNode::VComp(vcomp) => vcomp.fmt(f),
+            VNode::VList(vlist) => vlist.fmt(f),
+            VNode::VText(vtext) => vtext.fmt(f),
+            VNode::VRef(_) => f.write_str("VNode::VRef(_)"),
+        }
+    }
+}
+
+impl<COMP: Component> PartialEq for VNode<COMP> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (VNode::VComp(a), VNode::VComp(b)) => a == b,
+            (VNode::VList(a), VNode::VList(b)) => a == b,
+            (VNode::VText(a), VNode::VText(b)) => a == b,
+            (VNode::VRef(a), VNode::VRef(b)) => a == b,
+            _ => false,
+        }
+    }
+}
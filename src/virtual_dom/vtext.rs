@@ -0,0 +1,130 @@
+//! This module contains the implementation of a virtual text node `VText`.
+
+use super::dom_edit::{self, DomEdit, EditApplier};
+use super::{Key, VDiff, VNode};
+use crate::html::Component;
+use std::convert::TryInto;
+use stdweb::web::{Element, INode, Node, TextNode};
+
+/// A type representing text content in Yew's virtual DOM tree.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VText {
+    /// Contents of the text node.
+    pub text: String,
+    /// An optional key, letting a keyed `VList` reuse this node across
+    /// reorders instead of recreating it.
+    pub key: Option<Key>,
+    reference: Option<TextNode>,
+}
+
+impl VText {
+    /// Creates a new `VText` instance with the given content.
+    pub fn new(text: String) -> Self {
+        VText {
+            text,
+            key: None,
+            reference: None,
+        }
+    }
+
+    /// Attaches a key to this node.
+    pub fn with_key<K: Into<Key>>(mut self, key: K) -> Self {
+        self.key = Some(key.into());
+        self
+    }
+
+    /// Returns the key attached to this node, if any.
+    pub fn key(&self) -> Option<&Key> {
+        self.key.as_ref()
+    }
+
+    /// Removes the underlying text node from `parent`, returning the next
+    /// sibling so the caller can keep threading `previous_sibling` through.
+    pub fn detach(
+        &mut self,
+        parent: &Element,
+        applier: &mut dyn EditApplier,
+        edits: &mut Vec<DomEdit>,
+    ) -> Option<Node> {
+        let reference = self.reference.take()?;
+        let node: Node = reference.into();
+        let sibling = node.next_sibling();
+        dom_edit::record(edits, applier, parent, DomEdit::RemoveNode { node });
+        sibling
+    }
+
+    /// Scaffolds (or reuses) the underlying text node. `COMP` is the
+    /// component type the ancestor's `VNode` is parameterized over; `VText`
+    /// itself doesn't need to know it, since it never touches a `Scope`.
+    pub(crate) fn apply<COMP: Component>(
+        &mut self,
+        parent: &Element,
+        previous_sibling: Option<&Node>,
+        ancestor: Option<VNode<COMP>>,
+        applier: &mut dyn EditApplier,
+        edits: &mut Vec<DomEdit>,
+    ) -> Option<Node> {
+        match ancestor {
+            Some(VNode::VText(mut vtext)) if vtext.reference.is_some() => {
+                let reference = vtext.reference.take().unwrap();
+                if vtext.text == self.text {
+                    let node: Node = reference.clone().into();
+                    self.reference = Some(reference);
+                    Some(node)
+                } else {
+                    let old_node: Node = reference.into();
+                    dom_edit::record(edits, applier, parent, DomEdit::RemoveNode { node: old_node });
+                    self.create(parent, previous_sibling, applier, edits)
+                }
+            }
+            Some(mut vnode) => {
+                vnode.detach(parent, applier, edits);
+                self.create(parent, previous_sibling, applier, edits)
+            }
+            None => self.create(parent, previous_sibling, applier, edits),
+        }
+    }
+
+    fn create(
+        &mut self,
+        parent: &Element,
+        previous_sibling: Option<&Node>,
+        applier: &mut dyn EditApplier,
+        edits: &mut Vec<DomEdit>,
+    ) -> Option<Node> {
+        let created = dom_edit::record(
+            edits,
+            applier,
+            parent,
+            DomEdit::CreateTextNode {
+                text: self.text.clone(),
+            },
+        )
+        .expect("CreateTextNode always produces a node");
+        match previous_sibling.and_then(|before| before.next_sibling()) {
+            Some(sibling) => {
+                dom_edit::record(
+                    edits,
+                    applier,
+                    parent,
+                    DomEdit::InsertBefore {
+                        node: created.clone(),
+                        before: sibling,
+                    },
+                );
+            }
+            None => {
+                dom_edit::record(
+                    edits,
+                    applier,
+                    parent,
+                    DomEdit::AppendChild {
+                        node: created.clone(),
+                    },
+                );
+            }
+        }
+        self.reference = created.clone().try_into().ok();
+        Some(created)
+    }
+}